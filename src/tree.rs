@@ -1,9 +1,66 @@
+use std::collections::BinaryHeap;
+
 use vector::Vector2;
 
 use crate::{Rectangle, Positioned};
 
 const NODE_CAPACITY: usize = 4;
 
+fn squared_distance(a: &Vector2, b: &Vector2) -> f32 {
+    let dx = a.x - b.x;
+    let dy = a.y - b.y;
+    dx * dx + dy * dy
+}
+
+fn boundary_min_distance_squared(boundary: &Rectangle, point: &Vector2) -> f32 {
+    let clamped_x = point.x.clamp(boundary.center.x - boundary.half_dim.x, boundary.center.x + boundary.half_dim.x);
+    let clamped_y = point.y.clamp(boundary.center.y - boundary.half_dim.y, boundary.center.y + boundary.half_dim.y);
+
+    squared_distance(point, &Vector2::new(clamped_x, clamped_y))
+}
+
+/// Splits `boundary` into its North-West, North-East, South-West and
+/// South-East quarters, in that order. Shared by every tree variant's
+/// `subdivide` so the quadrant geometry only has to be correct in one place.
+fn subdivide_boundary(boundary: &Rectangle) -> [Rectangle; 4] {
+    let (px, py) = (boundary.center.x, boundary.center.y);
+    let (hx, hy) = (boundary.half_dim.x, boundary.half_dim.y);
+
+    let half_dim = Vector2::new(hx / 2.0, hy / 2.0);
+
+    [
+        Rectangle::new(Vector2::new(px - hx / 2.0, py - hy / 2.0), half_dim.clone()),
+        Rectangle::new(Vector2::new(px + hx / 2.0, py - hy / 2.0), half_dim.clone()),
+        Rectangle::new(Vector2::new(px - hx / 2.0, py + hy / 2.0), half_dim.clone()),
+        Rectangle::new(Vector2::new(px + hx / 2.0, py + hy / 2.0), half_dim.clone()),
+    ]
+}
+
+struct Neighbor<'a> {
+    entry: &'a dyn Positioned,
+    distance_sq: f32,
+}
+
+impl<'a> PartialEq for Neighbor<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.distance_sq == other.distance_sq
+    }
+}
+
+impl<'a> Eq for Neighbor<'a> {}
+
+impl<'a> PartialOrd for Neighbor<'a> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.distance_sq.partial_cmp(&other.distance_sq)
+    }
+}
+
+impl<'a> Ord for Neighbor<'a> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.partial_cmp(other).unwrap_or(std::cmp::Ordering::Equal)
+    }
+}
+
 pub struct Quadtree<'a> {
     boundary: Rectangle,
 
@@ -68,37 +125,749 @@ impl<'a> Quadtree<'a> {
         result
     }
 
+    /// Returns every entry within `radius` of `center`.
+    ///
+    /// Subtrees are pruned using the squared distance from `center` to the
+    /// closest point of their `boundary`, so this only touches quadrants the
+    /// circle could actually reach.
+    pub fn query_radius(&self, center: &Vector2, radius: f32) -> Vec<&'a dyn Positioned> {
+        let mut result = Vec::new();
+        let radius_sq = radius * radius;
+
+        if boundary_min_distance_squared(&self.boundary, center) > radius_sq {
+            return result;
+        }
+
+        for entry in &self.entries {
+            if squared_distance(&entry.position(), center) <= radius_sq {
+                result.push(*entry);
+            }
+        }
+
+        if self.quadrants.is_none() {
+            return result;
+        }
+
+        for quadrant in self.quadrants.as_ref().unwrap() {
+            result.append(&mut quadrant.query_radius(center, radius));
+        }
+
+        result
+    }
+
+    /// Returns the `k` entries closest to `point`, sorted nearest-first.
+    ///
+    /// Uses a best-first search: a max-heap holds the current `k` best
+    /// candidates keyed on squared distance, and quadrants are visited in
+    /// order of their closest possible point to `point`, skipping any whose
+    /// closest possible point is already farther than the current worst
+    /// candidate.
+    pub fn nearest(&self, point: &Vector2, k: usize) -> Vec<&'a dyn Positioned> {
+        let mut heap: BinaryHeap<Neighbor<'a>> = BinaryHeap::new();
+
+        if k > 0 {
+            self.nearest_into(point, k, &mut heap);
+        }
+
+        heap.into_sorted_vec().into_iter().map(|neighbor| neighbor.entry).collect()
+    }
+
+    fn nearest_into(&self, point: &Vector2, k: usize, heap: &mut BinaryHeap<Neighbor<'a>>) {
+        if heap.len() >= k {
+            if let Some(worst) = heap.peek() {
+                if boundary_min_distance_squared(&self.boundary, point) > worst.distance_sq {
+                    return;
+                }
+            }
+        }
+
+        for entry in &self.entries {
+            let distance_sq = squared_distance(&entry.position(), point);
+
+            if heap.len() < k {
+                heap.push(Neighbor { entry: *entry, distance_sq });
+            } else if let Some(worst) = heap.peek() {
+                if distance_sq < worst.distance_sq {
+                    heap.pop();
+                    heap.push(Neighbor { entry: *entry, distance_sq });
+                }
+            }
+        }
+
+        if let Some(quadrants) = &self.quadrants {
+            let mut ordered: Vec<&Box<Quadtree<'a>>> = quadrants.iter().collect();
+            ordered.sort_by(|a, b| {
+                boundary_min_distance_squared(&a.boundary, point)
+                    .partial_cmp(&boundary_min_distance_squared(&b.boundary, point))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+
+            for quadrant in ordered {
+                quadrant.nearest_into(point, k, heap);
+            }
+        }
+    }
+
+    fn subdivide(&mut self) {
+        let [nw, ne, sw, se] = subdivide_boundary(&self.boundary);
+
+        self.quadrants = Some([
+            Box::new(Quadtree::new(nw)),
+            Box::new(Quadtree::new(ne)),
+            Box::new(Quadtree::new(sw)),
+            Box::new(Quadtree::new(se)),
+        ]);
+    }
+}
+
+/// An entry that additionally carries a mass, for use with [`BarnesHutTree`].
+pub trait Weighted: Positioned {
+    fn mass(&self) -> f32;
+}
+
+/// A quadtree that caches a mass-weighted center of mass per node, so that
+/// [`approximate_force`](BarnesHutTree::approximate_force) can approximate
+/// far-away subtrees as a single body (the Barnes-Hut algorithm). This is an
+/// opt-in alternative to [`Quadtree`] for N-body style simulations.
+pub struct BarnesHutTree<'a> {
+    boundary: Rectangle,
+
+    entries: Vec<&'a dyn Weighted>,
+
+    quadrants: Option<[Box<BarnesHutTree<'a>>; 4]>,
+
+    total_mass: f32,
+    center_of_mass: Vector2,
+}
+
+impl<'a> BarnesHutTree<'a> {
+    pub fn new(boundary: Rectangle) -> BarnesHutTree<'a> {
+        BarnesHutTree {
+            boundary,
+            entries: Vec::new(),
+            quadrants: None,
+            total_mass: 0.0,
+            center_of_mass: Vector2::new(0.0, 0.0),
+        }
+    }
+
+    pub fn insert(&mut self, entry: &'a dyn Weighted) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.boundary.contains(entry) {
+            return Err("Entry not within bounds")?;
+        }
+
+        if self.entries.len() < NODE_CAPACITY && self.quadrants.is_none() {
+            self.entries.push(entry);
+            self.recompute_aggregate();
+            return Ok(());
+        }
+
+        if self.quadrants.is_none() {
+            self.subdivide();
+        }
+
+        for quadrant in self.quadrants.as_mut().unwrap() {
+            if quadrant.insert(entry).is_ok() {
+                self.recompute_aggregate();
+                return Ok(());
+            }
+        }
+
+        Err("This should not happen")?
+    }
+
+    /// Approximates the force `target` feels from every entry in this tree.
+    ///
+    /// Recurses from the root; a node is treated as a single body at its
+    /// `center_of_mass` (and `accumulate` is called once for it) whenever
+    /// `s / d < theta`, where `s` is the node's width and `d` is the distance
+    /// from `target` to the node's center of mass. Otherwise the entries
+    /// stored on the node itself are passed to `accumulate` one by one
+    /// (skipping any at `target`'s own position) and the node's quadrants
+    /// are visited individually.
+    pub fn approximate_force(&self, target: &dyn Positioned, theta: f32, mut accumulate: impl FnMut(Vector2, f32)) {
+        self.approximate_force_into(target, theta, &mut accumulate);
+    }
+
+    fn approximate_force_into(&self, target: &dyn Positioned, theta: f32, accumulate: &mut impl FnMut(Vector2, f32)) {
+        if self.total_mass == 0.0 {
+            return;
+        }
+
+        let target_position = target.position();
+        let d = squared_distance(&target_position, &self.center_of_mass).sqrt();
+        let s = 2.0 * self.boundary.half_dim.x;
+
+        if d > 0.0 && s / d < theta {
+            accumulate(self.center_of_mass.clone(), self.total_mass);
+            return;
+        }
+
+        // A leaf's entries live here, and `insert` also leaves up to
+        // `NODE_CAPACITY` entries sitting directly on this node even after
+        // it subdivides, so they must be accumulated here too — they aren't
+        // part of any quadrant's `total_mass`.
+        for entry in &self.entries {
+            if entry.position() != target_position {
+                accumulate(entry.position(), entry.mass());
+            }
+        }
+
+        if let Some(quadrants) = &self.quadrants {
+            for quadrant in quadrants {
+                quadrant.approximate_force_into(target, theta, accumulate);
+            }
+        }
+    }
+
+    fn recompute_aggregate(&mut self) {
+        let mut mass_sum = 0.0;
+        let mut weighted_x = 0.0;
+        let mut weighted_y = 0.0;
+
+        for entry in &self.entries {
+            let mass = entry.mass();
+            let position = entry.position();
+            mass_sum += mass;
+            weighted_x += position.x * mass;
+            weighted_y += position.y * mass;
+        }
+
+        if let Some(quadrants) = &self.quadrants {
+            for quadrant in quadrants {
+                mass_sum += quadrant.total_mass;
+                weighted_x += quadrant.center_of_mass.x * quadrant.total_mass;
+                weighted_y += quadrant.center_of_mass.y * quadrant.total_mass;
+            }
+        }
+
+        self.total_mass = mass_sum;
+        self.center_of_mass = if mass_sum > 0.0 {
+            Vector2::new(weighted_x / mass_sum, weighted_y / mass_sum)
+        } else {
+            Vector2::new(self.boundary.center.x, self.boundary.center.y)
+        };
+    }
+
     fn subdivide(&mut self) {
-        let (px, py) = (self.boundary.center.x, self.boundary.center.y);
-        let (hx, hy) = (self.boundary.half_dim.x, self.boundary.half_dim.y);
-
-        let half_dim = Vector2::new(hx / 2.0, hy / 2.0);
-
-        // North-West quadrant
-        let nw_center = Vector2::new(px - hx / 2.0, py - hy / 2.0);
-        let north_west = Box::new(Quadtree::new(
-            Rectangle::new(nw_center, half_dim.clone()))
-        );
-    
-        // North-East quadrant
-        let ne_center = Vector2::new(px + hx / 2.0, py - hy / 2.0);
-        let north_east = Box::new(Quadtree::new(
-            Rectangle::new(ne_center, half_dim.clone()))
-        );
-
-        // South-West quadrant
-        let sw_center = Vector2::new(px - hx / 2.0, py + hy / 2.0);
-        let south_west = Box::new(Quadtree::new(
-            Rectangle::new(sw_center, half_dim.clone()))
-        );
-
-        // South-East quadrant
-        let se_center = Vector2::new(px + hx / 2.0, py + hy / 2.0);
-        let south_east = Box::new(Quadtree::new(
-            Rectangle::new(se_center, half_dim.clone()))
-        );
-
-        self.quadrants = Some([north_west, north_east, south_west, south_east]);
+        let [nw, ne, sw, se] = subdivide_boundary(&self.boundary);
+
+        self.quadrants = Some([
+            Box::new(BarnesHutTree::new(nw)),
+            Box::new(BarnesHutTree::new(ne)),
+            Box::new(BarnesHutTree::new(sw)),
+            Box::new(BarnesHutTree::new(se)),
+        ]);
+    }
+}
+
+/// An owned, arena-backed alternative to [`Quadtree`].
+///
+/// [`Quadtree`] borrows its entries (`&'a dyn Positioned`), which forces
+/// callers to keep every element alive elsewhere and prevents the tree from
+/// being moved across threads or returned from a function. `owned::Quadtree`
+/// instead owns its elements in a single `Vec<T>` and stores its nodes in a
+/// flat arena addressed by index, so the whole structure is a handful of
+/// contiguous allocations rather than a chain of `Box`es.
+pub mod owned {
+    use vector::Vector2;
+
+    use crate::{Rectangle, Positioned};
+
+    use super::NODE_CAPACITY;
+
+    type NodeId = usize;
+
+    struct Node {
+        boundary: Rectangle,
+        entries: Vec<usize>,
+        children: Option<[NodeId; 4]>,
+    }
+
+    pub struct Quadtree<T: Positioned> {
+        nodes: Vec<Node>,
+        // A removed entry's slot becomes `None` and its index is pushed onto
+        // `free_indices`, so repeated remove+insert churn reuses storage
+        // instead of growing `items` forever; `count` tracks the live total.
+        items: Vec<Option<T>>,
+        free_indices: Vec<usize>,
+        count: usize,
+    }
+
+    impl<T: Positioned> Quadtree<T> {
+        pub fn new(boundary: Rectangle) -> Quadtree<T> {
+            Quadtree {
+                nodes: vec![Node { boundary, entries: Vec::new(), children: None }],
+                items: Vec::new(),
+                free_indices: Vec::new(),
+                count: 0,
+            }
+        }
+
+        fn store(&mut self, entry: T) -> usize {
+            if let Some(item_index) = self.free_indices.pop() {
+                self.items[item_index] = Some(entry);
+                item_index
+            } else {
+                self.items.push(Some(entry));
+                self.items.len() - 1
+            }
+        }
+
+        pub fn insert(&mut self, entry: T) -> Result<(), Box<dyn std::error::Error>> {
+            if !Self::contains_point(&self.nodes[0].boundary, &entry.position()) {
+                return Err("Entry not within bounds")?;
+            }
+
+            let position = entry.position();
+            let item_index = self.store(entry);
+            self.insert_into(0, item_index, &position)?;
+            self.count += 1;
+            Ok(())
+        }
+
+        fn insert_into(&mut self, node_id: NodeId, item_index: usize, position: &Vector2) -> Result<(), Box<dyn std::error::Error>> {
+            if self.nodes[node_id].entries.len() < NODE_CAPACITY && self.nodes[node_id].children.is_none() {
+                self.nodes[node_id].entries.push(item_index);
+                return Ok(());
+            }
+
+            if self.nodes[node_id].children.is_none() {
+                self.subdivide(node_id);
+            }
+
+            let children = self.nodes[node_id].children.unwrap();
+
+            for child_id in children {
+                if Self::contains_point(&self.nodes[child_id].boundary, position) {
+                    return self.insert_into(child_id, item_index, position);
+                }
+            }
+
+            Err("This should not happen")?
+        }
+
+        /// Returns the number of entries currently stored in the tree.
+        pub fn len(&self) -> usize {
+            self.count
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.count == 0
+        }
+
+        /// Returns references to every entry within `range`.
+        pub fn query(&self, range: &Rectangle) -> Vec<&T> {
+            let mut result = Vec::new();
+            self.query_into(0, range, &mut result);
+            result
+        }
+
+        fn query_into<'s>(&'s self, node_id: NodeId, range: &Rectangle, result: &mut Vec<&'s T>) {
+            let node = &self.nodes[node_id];
+            if !node.boundary.intersects(range) {
+                return;
+            }
+
+            for &item_index in &node.entries {
+                let item = self.items[item_index].as_ref().unwrap();
+                if range.contains(item) {
+                    result.push(item);
+                }
+            }
+
+            if let Some(children) = node.children {
+                for child_id in children {
+                    self.query_into(child_id, range, result);
+                }
+            }
+        }
+
+        /// Removes the first stored entry at the same position as `entry`.
+        ///
+        /// Returns `true` if a matching entry was found and removed. After a
+        /// removal, each branch node on the removal path whose children are
+        /// all leaves, and which together with those children holds at most
+        /// `NODE_CAPACITY` entries, is collapsed back into a leaf, and the
+        /// freed storage slot is reused by a later `insert`.
+        pub fn remove(&mut self, entry: &dyn Positioned) -> bool {
+            let target = entry.position();
+            match self.take_matching(0, &target) {
+                Some(item_index) => {
+                    self.items[item_index] = None;
+                    self.free_indices.push(item_index);
+                    true
+                }
+                None => false,
+            }
+        }
+
+        fn take_matching(&mut self, node_id: NodeId, target: &Vector2) -> Option<usize> {
+            if let Some(pos) = self.nodes[node_id].entries.iter()
+                .position(|&item_index| self.items[item_index].as_ref().unwrap().position() == *target)
+            {
+                let item_index = self.nodes[node_id].entries.remove(pos);
+                self.count -= 1;
+                self.try_collapse(node_id);
+                return Some(item_index);
+            }
+
+            if let Some(children) = self.nodes[node_id].children {
+                for child_id in children {
+                    if Self::contains_point(&self.nodes[child_id].boundary, target) {
+                        if let Some(item_index) = self.take_matching(child_id, target) {
+                            self.try_collapse(node_id);
+                            return Some(item_index);
+                        }
+                    }
+                }
+            }
+
+            None
+        }
+
+        fn try_collapse(&mut self, node_id: NodeId) {
+            let children = match self.nodes[node_id].children {
+                Some(children) => children,
+                None => return,
+            };
+
+            if children.iter().any(|&child_id| self.nodes[child_id].children.is_some()) {
+                return;
+            }
+
+            let total: usize = self.nodes[node_id].entries.len()
+                + children.iter().map(|&child_id| self.nodes[child_id].entries.len()).sum::<usize>();
+
+            if total > NODE_CAPACITY {
+                return;
+            }
+
+            for child_id in children {
+                let mut child_entries = std::mem::take(&mut self.nodes[child_id].entries);
+                self.nodes[node_id].entries.append(&mut child_entries);
+            }
+
+            self.nodes[node_id].children = None;
+        }
+
+        fn contains_point(boundary: &Rectangle, point: &Vector2) -> bool {
+            let (min_x, max_x) = (boundary.center.x - boundary.half_dim.x, boundary.center.x + boundary.half_dim.x);
+            let (min_y, max_y) = (boundary.center.y - boundary.half_dim.y, boundary.center.y + boundary.half_dim.y);
+
+            point.x >= min_x && point.x <= max_x && point.y >= min_y && point.y <= max_y
+        }
+
+        fn subdivide(&mut self, node_id: NodeId) {
+            let [nw, ne, sw, se] = super::subdivide_boundary(&self.nodes[node_id].boundary);
+
+            let nw_id = self.push_node(nw);
+            let ne_id = self.push_node(ne);
+            let sw_id = self.push_node(sw);
+            let se_id = self.push_node(se);
+
+            self.nodes[node_id].children = Some([nw_id, ne_id, sw_id, se_id]);
+        }
+
+        fn push_node(&mut self, boundary: Rectangle) -> NodeId {
+            self.nodes.push(Node { boundary, entries: Vec::new(), children: None });
+            self.nodes.len() - 1
+        }
+    }
+
+    /// An entry whose position can be updated in place, required by
+    /// [`Quadtree::relocate`].
+    pub trait Movable: Positioned {
+        fn set_position(&mut self, position: Vector2);
+    }
+
+    impl Movable for Vector2 {
+        fn set_position(&mut self, position: Vector2) {
+            self.x = position.x;
+            self.y = position.y;
+        }
+    }
+
+    impl<T: Positioned + Movable> Quadtree<T> {
+        /// Moves the entry at `entry`'s current position to `new_pos`,
+        /// re-placing it in the tree. Returns `false` (and leaves the tree
+        /// unchanged) if no matching entry is found, or if `new_pos` falls
+        /// outside the tree's bounds.
+        pub fn relocate(&mut self, entry: &dyn Positioned, new_pos: Vector2) -> bool {
+            if !Self::contains_point(&self.nodes[0].boundary, &new_pos) {
+                return false;
+            }
+
+            let target = entry.position();
+            match self.take_matching(0, &target) {
+                Some(item_index) => {
+                    self.items[item_index].as_mut().unwrap().set_position(new_pos.clone());
+                    let inserted = self.insert_into(0, item_index, &new_pos).is_ok();
+                    if inserted {
+                        self.count += 1;
+                    }
+                    inserted
+                }
+                None => false,
+            }
+        }
+    }
+
+    /// An entry that occupies an axis-aligned area rather than a single point.
+    pub trait Bounded: Positioned {
+        fn bounds(&self) -> Rectangle;
+    }
+
+    impl<T: Positioned + Bounded> Quadtree<T> {
+        /// Inserts `entry` using its `bounds()` instead of a single point.
+        ///
+        /// The entry is placed at the deepest node whose boundary fully
+        /// contains its box; an entry that straddles more than one quadrant
+        /// is kept at the parent node instead of being forced into one side.
+        pub fn insert_bounded(&mut self, entry: T) -> Result<(), Box<dyn std::error::Error>> {
+            if !Self::rect_contains(&self.nodes[0].boundary, &entry.bounds()) {
+                return Err("Entry not within bounds")?;
+            }
+
+            let bounds = entry.bounds();
+            let item_index = self.store(entry);
+            self.insert_bounded_into(0, item_index, &bounds);
+            self.count += 1;
+            Ok(())
+        }
+
+        fn insert_bounded_into(&mut self, node_id: NodeId, item_index: usize, bounds: &Rectangle) {
+            if self.nodes[node_id].entries.len() < NODE_CAPACITY && self.nodes[node_id].children.is_none() {
+                self.nodes[node_id].entries.push(item_index);
+                return;
+            }
+
+            if self.nodes[node_id].children.is_none() {
+                self.subdivide(node_id);
+            }
+
+            let children = self.nodes[node_id].children.unwrap();
+
+            for child_id in children {
+                if Self::rect_contains(&self.nodes[child_id].boundary, bounds) {
+                    self.insert_bounded_into(child_id, item_index, bounds);
+                    return;
+                }
+            }
+
+            self.nodes[node_id].entries.push(item_index);
+        }
+
+        /// Returns references to every entry whose box intersects `range`,
+        /// including entries kept at an ancestor node because they straddled
+        /// more than one of its quadrants.
+        pub fn query_bounded(&self, range: &Rectangle) -> Vec<&T> {
+            let mut result = Vec::new();
+            self.query_bounded_into(0, range, &mut result);
+            result
+        }
+
+        fn query_bounded_into<'s>(&'s self, node_id: NodeId, range: &Rectangle, result: &mut Vec<&'s T>) {
+            let node = &self.nodes[node_id];
+            if !node.boundary.intersects(range) {
+                return;
+            }
+
+            for &item_index in &node.entries {
+                let item = self.items[item_index].as_ref().unwrap();
+                if range.intersects(&item.bounds()) {
+                    result.push(item);
+                }
+            }
+
+            if let Some(children) = node.children {
+                for child_id in children {
+                    self.query_bounded_into(child_id, range, result);
+                }
+            }
+        }
+
+        fn rect_contains(boundary: &Rectangle, bounds: &Rectangle) -> bool {
+            let (bx_min, bx_max) = (boundary.center.x - boundary.half_dim.x, boundary.center.x + boundary.half_dim.x);
+            let (by_min, by_max) = (boundary.center.y - boundary.half_dim.y, boundary.center.y + boundary.half_dim.y);
+
+            let (ix_min, ix_max) = (bounds.center.x - bounds.half_dim.x, bounds.center.x + bounds.half_dim.x);
+            let (iy_min, iy_max) = (bounds.center.y - bounds.half_dim.y, bounds.center.y + bounds.half_dim.y);
+
+            ix_min >= bx_min && ix_max <= bx_max && iy_min >= by_min && iy_max <= by_max
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_insert_and_query() {
+            let mut tree = Quadtree::new(Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0)));
+            tree.insert(Vector2::new(50.0, 50.0)).unwrap();
+            tree.insert(Vector2::new(10.0, 10.0)).unwrap();
+
+            assert_eq!(tree.len(), 2);
+
+            let result = tree.query(&Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0)));
+            assert_eq!(result.len(), 2);
+        }
+
+        #[test]
+        fn test_insert_out_of_bounds() {
+            let mut tree: Quadtree<Vector2> = Quadtree::new(Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0)));
+            assert!(tree.insert(Vector2::new(150.0, 150.0)).is_err());
+        }
+
+        #[test]
+        fn test_remove() {
+            let mut tree = Quadtree::new(Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0)));
+            let entry = Vector2::new(50.0, 50.0);
+            tree.insert(entry.clone()).unwrap();
+
+            assert!(tree.remove(&entry));
+            assert_eq!(tree.len(), 0);
+            assert!(!tree.remove(&entry));
+        }
+
+        #[test]
+        fn test_remove_reuses_freed_slot() {
+            let mut tree = Quadtree::new(Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0)));
+
+            for _ in 0..50 {
+                let entry = Vector2::new(10.0, 10.0);
+                tree.insert(entry.clone()).unwrap();
+                assert!(tree.remove(&entry));
+            }
+
+            assert_eq!(tree.len(), 0);
+            assert_eq!(tree.items.len(), 1, "repeated remove+insert churn must reuse the freed slot, not grow items");
+        }
+
+        #[test]
+        fn test_collapse_after_remove() {
+            let mut tree = Quadtree::new(Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0)));
+            let entries = [
+                Vector2::new(-50.0, -50.0),
+                Vector2::new(50.0, -50.0),
+                Vector2::new(-50.0, 50.0),
+                Vector2::new(50.0, 50.0),
+                Vector2::new(25.0, 25.0),
+            ];
+            for entry in &entries {
+                tree.insert(entry.clone()).unwrap();
+            }
+            assert_eq!(tree.len(), 5);
+
+            assert!(tree.nodes[0].children.is_some());
+
+            assert!(tree.remove(&entries[4]));
+            assert_eq!(tree.len(), 4);
+            assert!(tree.nodes[0].children.is_none(), "root must collapse back into a leaf");
+
+            let result = tree.query(&Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0)));
+            assert_eq!(result.len(), 4);
+        }
+
+        #[test]
+        fn test_relocate() {
+            let mut tree = Quadtree::new(Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0)));
+            let entry = Vector2::new(-50.0, -50.0);
+            tree.insert(entry.clone()).unwrap();
+
+            assert!(tree.relocate(&entry, Vector2::new(50.0, 50.0)));
+            assert_eq!(tree.len(), 1);
+
+            let old_range = tree.query(&Rectangle::new(Vector2::new(-50.0, -50.0), Vector2::new(10.0, 10.0)));
+            assert_eq!(old_range.len(), 0, "relocated entry must not still be reported at its old position");
+
+            let new_range = tree.query(&Rectangle::new(Vector2::new(50.0, 50.0), Vector2::new(10.0, 10.0)));
+            assert_eq!(new_range.len(), 1);
+            assert_eq!(new_range[0].position(), Vector2::new(50.0, 50.0).position());
+        }
+
+        struct Collider {
+            bounds: Rectangle,
+        }
+
+        impl Positioned for Collider {
+            fn position(&self) -> Vector2 {
+                Vector2::new(self.bounds.center.x, self.bounds.center.y)
+            }
+        }
+
+        impl Bounded for Collider {
+            fn bounds(&self) -> Rectangle {
+                Rectangle::new(
+                    Vector2::new(self.bounds.center.x, self.bounds.center.y),
+                    Vector2::new(self.bounds.half_dim.x, self.bounds.half_dim.y),
+                )
+            }
+        }
+
+        #[test]
+        fn test_insert_bounded_and_query() {
+            let mut tree = Quadtree::new(Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0)));
+            tree.insert_bounded(Collider {
+                bounds: Rectangle::new(Vector2::new(50.0, 50.0), Vector2::new(5.0, 5.0)),
+            }).unwrap();
+
+            let result = tree.query_bounded(&Rectangle::new(Vector2::new(50.0, 50.0), Vector2::new(10.0, 10.0)));
+            assert_eq!(result.len(), 1);
+        }
+
+        #[test]
+        fn test_insert_bounded_straddling_kept_at_parent() {
+            let mut tree = Quadtree::new(Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0)));
+            // Fill the root to `NODE_CAPACITY` so the next insert subdivides it.
+            for (x, y) in [(-50.0, -50.0), (50.0, -50.0), (-50.0, 50.0), (50.0, 50.0)] {
+                tree.insert_bounded(Collider {
+                    bounds: Rectangle::new(Vector2::new(x, y), Vector2::new(5.0, 5.0)),
+                }).unwrap();
+            }
+
+            // Large enough to straddle all four quadrants, so it must stay at the root.
+            tree.insert_bounded(Collider {
+                bounds: Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(80.0, 80.0)),
+            }).unwrap();
+
+            assert!(tree.nodes[0].children.is_some());
+            assert_eq!(tree.nodes[0].entries.len(), 5);
+
+            let result = tree.query_bounded(&Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(1.0, 1.0)));
+            assert_eq!(result.len(), 1);
+        }
+
+        #[test]
+        fn test_insert_bounded_does_not_eagerly_subdivide() {
+            // A single `insert_bounded` call must only subdivide when
+            // `NODE_CAPACITY` is actually exceeded, same as plain `insert` —
+            // not eagerly descend to the deepest quadrant that still
+            // contains the box.
+            let mut tree = Quadtree::new(Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0)));
+            tree.insert_bounded(Collider {
+                bounds: Rectangle::new(Vector2::new(50.0, 50.0), Vector2::new(5.0, 5.0)),
+            }).unwrap();
+
+            assert_eq!(tree.nodes.len(), 1);
+        }
+
+        #[test]
+        fn test_query_ignores_entries_outside_point_but_within_box() {
+            // Plain `query` only ever sees `T: Positioned`, so it must stay a
+            // point-based range check, not the box-intersection check used by
+            // `query_bounded` — an entry whose bounds reach into `range` but
+            // whose own position does not must not show up here.
+            let mut tree = Quadtree::new(Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0)));
+            tree.insert_bounded(Collider {
+                bounds: Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(80.0, 80.0)),
+            }).unwrap();
+
+            let result = tree.query(&Rectangle::new(Vector2::new(-75.0, -75.0), Vector2::new(5.0, 5.0)));
+            assert_eq!(result.len(), 0);
+        }
+
     }
 }
 
@@ -134,6 +903,138 @@ mod tests {
         assert_eq!(result[0].position(), entry.position());
     }
 
+    struct Body {
+        position: Vector2,
+        mass: f32,
+    }
+
+    impl Positioned for Body {
+        fn position(&self) -> Vector2 {
+            self.position.clone()
+        }
+    }
+
+    impl Weighted for Body {
+        fn mass(&self) -> f32 {
+            self.mass
+        }
+    }
+
+    #[test]
+    fn test_barnes_hut_center_of_mass() {
+        let mut tree = BarnesHutTree::new(Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0)));
+        let a = Body { position: Vector2::new(-10.0, 0.0), mass: 1.0 };
+        let b = Body { position: Vector2::new(10.0, 0.0), mass: 1.0 };
+        tree.insert(&a).unwrap();
+        tree.insert(&b).unwrap();
+
+        assert_eq!(tree.total_mass, 2.0);
+        assert_eq!(tree.center_of_mass.x, 0.0);
+        assert_eq!(tree.center_of_mass.y, 0.0);
+    }
+
+    #[test]
+    fn test_barnes_hut_approximate_force_far_node() {
+        let mut tree = BarnesHutTree::new(Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0)));
+        let bodies: Vec<Body> = (0..8)
+            .map(|i| Body { position: Vector2::new(50.0 + i as f32, 50.0 + i as f32), mass: 1.0 })
+            .collect();
+        for body in &bodies {
+            tree.insert(body).unwrap();
+        }
+
+        let target = Body { position: Vector2::new(-50.0, -50.0), mass: 1.0 };
+        let mut calls = 0;
+        tree.approximate_force(&target, 2.0, |_, _| calls += 1);
+
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_barnes_hut_approximate_force_includes_root_entries() {
+        // The first 4 bodies fill `root.entries` to `NODE_CAPACITY` and stay
+        // there even after the 5th insert forces a subdivide — `insert`
+        // doesn't redistribute already-placed entries into children. A small
+        // `theta` forces `approximate_force` to recurse into the root's
+        // quadrants instead of treating the whole root as one body, which
+        // must not cause those 4 resident entries to be skipped.
+        let mut tree = BarnesHutTree::new(Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0)));
+        let resident = vec![
+            Body { position: Vector2::new(1.0, 1.0), mass: 1.0 },
+            Body { position: Vector2::new(2.0, 1.0), mass: 1.0 },
+            Body { position: Vector2::new(1.0, 2.0), mass: 1.0 },
+            Body { position: Vector2::new(2.0, 2.0), mass: 1.0 },
+        ];
+        for body in &resident {
+            tree.insert(body).unwrap();
+        }
+
+        let child_bodies = vec![
+            Body { position: Vector2::new(50.0, 50.0), mass: 1.0 },
+            Body { position: Vector2::new(51.0, 50.0), mass: 1.0 },
+            Body { position: Vector2::new(50.0, 51.0), mass: 1.0 },
+            Body { position: Vector2::new(51.0, 51.0), mass: 1.0 },
+        ];
+        for body in &child_bodies {
+            tree.insert(body).unwrap();
+        }
+
+        let target = Body { position: Vector2::new(-90.0, -90.0), mass: 1.0 };
+        let mut total_mass = 0.0;
+        tree.approximate_force(&target, 0.01, |_, mass| total_mass += mass);
+
+        assert_eq!(total_mass, 8.0);
+    }
+
+    #[test]
+    fn test_query_radius() {
+        let mut tree = Quadtree::new(Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0)));
+        let close = Vector2::new(5.0, 0.0);
+        let far = Vector2::new(50.0, 50.0);
+        tree.insert(&close).unwrap();
+        tree.insert(&far).unwrap();
+
+        let result = tree.query_radius(&Vector2::new(0.0, 0.0), 10.0);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].position(), close.position());
+    }
+
+    #[test]
+    fn test_query_radius_none_in_range() {
+        let mut tree = Quadtree::new(Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0)));
+        let entry = Vector2::new(50.0, 50.0);
+        tree.insert(&entry).unwrap();
+
+        let result = tree.query_radius(&Vector2::new(0.0, 0.0), 10.0);
+        assert_eq!(result.len(), 0);
+    }
+
+    #[test]
+    fn test_nearest() {
+        let mut tree = Quadtree::new(Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0)));
+        let a = Vector2::new(10.0, 10.0);
+        let b = Vector2::new(20.0, 20.0);
+        let c = Vector2::new(-40.0, -40.0);
+        tree.insert(&a).unwrap();
+        tree.insert(&b).unwrap();
+        tree.insert(&c).unwrap();
+
+        let result = tree.nearest(&Vector2::new(0.0, 0.0), 2);
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].position(), a.position());
+        assert_eq!(result[1].position(), b.position());
+    }
+
+    #[test]
+    fn test_nearest_more_than_available() {
+        let mut tree = Quadtree::new(Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0)));
+        let entry = Vector2::new(5.0, 5.0);
+        tree.insert(&entry).unwrap();
+
+        let result = tree.nearest(&Vector2::new(0.0, 0.0), 5);
+        assert_eq!(result.len(), 1);
+    }
+
     #[test]
     fn test_query_out_of_bounds() {
         let mut tree = Quadtree::new(Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0)));